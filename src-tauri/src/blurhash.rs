@@ -0,0 +1,121 @@
+use crate::errors::{AppError, AppResult};
+use image::{imageops::FilterType, DynamicImage, GenericImageView};
+
+const BASE83_CHARS: &[u8] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz#$%*+,-.:;=?@[]^_{|}~";
+
+/// Blurhash encoder: produces a short string placeholder for an image,
+/// following the reference algorithm at https://blurha.sh.
+pub struct BlurhashHelper;
+
+impl BlurhashHelper {
+    /// Encode `img` into a blurhash string using `x_components` x `y_components`
+    /// basis functions (each in `1..=9`).
+    pub fn encode(img: &DynamicImage, x_components: u32, y_components: u32) -> AppResult<String> {
+        if !(1..=9).contains(&x_components) || !(1..=9).contains(&y_components) {
+            return Err(AppError::ConversionFailed(
+                "blurhash component counts must be between 1 and 9".to_string(),
+            ));
+        }
+
+        // Blurhash only needs a coarse representation; downscale for speed.
+        let small = img.resize(100, 100, FilterType::Triangle).to_rgb8();
+        let (width, height) = small.dimensions();
+
+        let mut factors = Vec::with_capacity((x_components * y_components) as usize);
+        for y in 0..y_components {
+            for x in 0..x_components {
+                let normalization = if x == 0 && y == 0 { 1.0 } else { 2.0 };
+                let (mut r, mut g, mut b) = (0.0f64, 0.0f64, 0.0f64);
+
+                for py in 0..height {
+                    for px in 0..width {
+                        let basis = normalization
+                            * (std::f64::consts::PI * x as f64 * px as f64 / width as f64).cos()
+                            * (std::f64::consts::PI * y as f64 * py as f64 / height as f64).cos();
+                        let pixel = small.get_pixel(px, py);
+                        r += basis * srgb_to_linear(pixel[0]);
+                        g += basis * srgb_to_linear(pixel[1]);
+                        b += basis * srgb_to_linear(pixel[2]);
+                    }
+                }
+
+                let scale = 1.0 / (width as f64 * height as f64);
+                factors.push((r * scale, g * scale, b * scale));
+            }
+        }
+
+        Ok(encode_factors(&factors, x_components, y_components))
+    }
+}
+
+fn encode_factors(factors: &[(f64, f64, f64)], x_components: u32, y_components: u32) -> String {
+    let mut result = String::new();
+    let size_flag = (x_components - 1) + (y_components - 1) * 9;
+    result.push_str(&base83_encode(size_flag, 1));
+
+    let dc = factors[0];
+    let ac = &factors[1..];
+
+    let max_value = if ac.is_empty() {
+        result.push_str(&base83_encode(0, 1));
+        1.0
+    } else {
+        let actual_max = ac
+            .iter()
+            .flat_map(|(r, g, b)| [r.abs(), g.abs(), b.abs()])
+            .fold(0.0f64, f64::max);
+        let quantized = ((actual_max * 166.0 - 0.5).floor().clamp(0.0, 82.0)) as u32;
+        result.push_str(&base83_encode(quantized, 1));
+        (quantized as f64 + 1.0) / 166.0
+    };
+
+    result.push_str(&base83_encode(encode_dc(dc), 4));
+    for &(r, g, b) in ac {
+        result.push_str(&base83_encode(encode_ac(r, g, b, max_value), 2));
+    }
+
+    result
+}
+
+fn srgb_to_linear(value: u8) -> f64 {
+    let v = value as f64 / 255.0;
+    if v <= 0.04045 {
+        v / 12.92
+    } else {
+        ((v + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(value: f64) -> u32 {
+    let v = value.clamp(0.0, 1.0);
+    let encoded = if v <= 0.0031308 {
+        v * 12.92
+    } else {
+        1.055 * v.powf(1.0 / 2.4) - 0.055
+    };
+    (encoded * 255.0 + 0.5).floor().clamp(0.0, 255.0) as u32
+}
+
+fn encode_dc(dc: (f64, f64, f64)) -> u32 {
+    (linear_to_srgb(dc.0) << 16) | (linear_to_srgb(dc.1) << 8) | linear_to_srgb(dc.2)
+}
+
+fn encode_ac(r: f64, g: f64, b: f64, max_value: f64) -> u32 {
+    let quantize = |value: f64| -> u32 {
+        signed_pow(value / max_value, 0.5).mul_add(9.0, 9.5).floor().clamp(0.0, 18.0) as u32
+    };
+    quantize(r) * 19 * 19 + quantize(g) * 19 + quantize(b)
+}
+
+fn signed_pow(value: f64, exponent: f64) -> f64 {
+    value.abs().powf(exponent).copysign(value)
+}
+
+fn base83_encode(mut value: u32, length: usize) -> String {
+    let mut chars = vec![0u8; length];
+    for slot in chars.iter_mut().rev() {
+        *slot = BASE83_CHARS[(value % 83) as usize];
+        value /= 83;
+    }
+    String::from_utf8(chars).expect("base83 alphabet is ASCII")
+}
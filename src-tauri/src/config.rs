@@ -2,12 +2,91 @@ use serde::{Deserialize, Serialize};
 use std::fs;
 use crate::constants::*;
 
+/// Output image format produced by a conversion. Determines both the temp
+/// file extension and the encoder used to write the final image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    Jpeg,
+    Png,
+    WebP,
+    Avif,
+}
+
+impl OutputFormat {
+    pub const ALL: [OutputFormat; 4] = [OutputFormat::Jpeg, OutputFormat::Png, OutputFormat::WebP, OutputFormat::Avif];
+
+    /// File extension (without the leading dot) used for the converted output.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            OutputFormat::Jpeg => "jpg",
+            OutputFormat::Png => "png",
+            OutputFormat::WebP => "webp",
+            OutputFormat::Avif => "avif",
+        }
+    }
+
+    /// The value passed to `sips -s format <value>` on macOS, or `None` if
+    /// `sips` has no encoder for this format (e.g. AVIF).
+    pub fn sips_format(&self) -> Option<&'static str> {
+        match self {
+            OutputFormat::Jpeg => Some("jpeg"),
+            OutputFormat::Png => Some("png"),
+            OutputFormat::WebP => Some("webp"),
+            OutputFormat::Avif => None,
+        }
+    }
+
+    /// Whether `sips -s formatOptions <quality>` is a valid flag for this
+    /// format. `sips` only accepts it for the JPEG family; it errors out
+    /// (and exits non-zero) if passed for PNG or WebP.
+    pub fn supports_quality(&self) -> bool {
+        matches!(self, OutputFormat::Jpeg)
+    }
+}
+
+impl Default for OutputFormat {
+    fn default() -> Self {
+        OutputFormat::Jpeg
+    }
+}
+
+/// Downscale preset applied to the converted image, sized to the longest edge.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "value")]
+pub enum ResizePreset {
+    Small,
+    Medium,
+    Large,
+    MaxEdge(u32),
+}
+
+impl ResizePreset {
+    /// Target size, in pixels, of the longest edge after resizing.
+    pub fn max_edge(&self) -> u32 {
+        match self {
+            ResizePreset::Small => 640,
+            ResizePreset::Medium => 1280,
+            ResizePreset::Large => 2560,
+            ResizePreset::MaxEdge(pixels) => *pixels,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConversionConfig {
     #[serde(rename = "jpegQuality")]
     pub jpeg_quality: u8,
     #[serde(rename = "maxFileSizeMB")]
     pub max_file_size_mb: u64,
+    #[serde(rename = "outputFormat", default)]
+    pub output_format: OutputFormat,
+    #[serde(rename = "preserveMetadata", default)]
+    pub preserve_metadata: bool,
+    #[serde(rename = "resize", default)]
+    pub resize: Option<ResizePreset>,
+    #[serde(rename = "computeBlurhash", default)]
+    pub compute_blurhash: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -41,6 +120,10 @@ impl Default for AppConfig {
             conversion: ConversionConfig {
                 jpeg_quality: DEFAULT_JPEG_QUALITY,
                 max_file_size_mb: DEFAULT_MAX_FILE_SIZE_MB,
+                output_format: OutputFormat::default(),
+                preserve_metadata: DEFAULT_PRESERVE_METADATA,
+                resize: None,
+                compute_blurhash: DEFAULT_COMPUTE_BLURHASH,
             },
             ui: UiConfig {
                 window_width: DEFAULT_WINDOW_WIDTH,
@@ -17,6 +17,10 @@ pub const HEIC_BRANDS: &[&[u8]] = &[
 // Conversion defaults
 pub const DEFAULT_JPEG_QUALITY: u8 = 90;
 pub const DEFAULT_MAX_FILE_SIZE_MB: u64 = 100;
+pub const DEFAULT_PRESERVE_METADATA: bool = false;
+pub const DEFAULT_COMPUTE_BLURHASH: bool = false;
+pub const BLURHASH_X_COMPONENTS: u32 = 4;
+pub const BLURHASH_Y_COMPONENTS: u32 = 3;
 
 // UI defaults  
 pub const DEFAULT_WINDOW_WIDTH: u32 = 600;
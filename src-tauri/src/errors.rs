@@ -50,6 +50,12 @@ impl From<serde_json::Error> for AppError {
     }
 }
 
+impl From<image::ImageError> for AppError {
+    fn from(error: image::ImageError) -> Self {
+        AppError::ConversionFailed(error.to_string())
+    }
+}
+
 impl From<AppError> for String {
     fn from(error: AppError) -> Self {
         error.to_string()
@@ -0,0 +1,366 @@
+use crate::errors::{AppError, AppResult};
+use std::fs;
+use std::path::Path;
+
+/// Minimal ISOBMFF/Exif reader and JPEG APP1 writer used to carry capture
+/// metadata (orientation, GPS, timestamp) across a HEIC -> JPEG conversion.
+///
+/// HEIC stores its Exif payload as an `Exif`-typed item inside the `meta`
+/// box; this does not attempt to parse the full ISOBMFF spec, just enough
+/// of it (box walking, `iinf`, `iloc`) to locate that item's bytes.
+pub struct ExifHelper;
+
+struct IsoBox {
+    box_type: [u8; 4],
+    body_start: usize,
+    body_end: usize,
+}
+
+impl ExifHelper {
+    /// Extract the raw Exif TIFF block (the bytes that would follow the
+    /// `Exif\0\0` tag in a JPEG APP1 segment) embedded in a HEIC file, if any.
+    pub fn extract_exif(file_path: &Path) -> AppResult<Option<Vec<u8>>> {
+        let data = fs::read(file_path)?;
+
+        let meta = match Self::find_box(&data, 0, data.len(), b"meta")? {
+            Some(b) => b,
+            None => return Ok(None),
+        };
+
+        // `meta` is a FullBox: a 4-byte version/flags header precedes its children.
+        let children_start = meta.body_start + 4;
+        if children_start > meta.body_end {
+            return Ok(None);
+        }
+
+        let iinf = match Self::find_box(&data, children_start, meta.body_end, b"iinf")? {
+            Some(b) => b,
+            None => return Ok(None),
+        };
+        let iloc = match Self::find_box(&data, children_start, meta.body_end, b"iloc")? {
+            Some(b) => b,
+            None => return Ok(None),
+        };
+
+        let exif_item_id = match Self::find_exif_item_id(&data, &iinf)? {
+            Some(id) => id,
+            None => return Ok(None),
+        };
+        let (offset, length) = match Self::find_item_location(&data, &iloc, exif_item_id)? {
+            Some(loc) => loc,
+            None => return Ok(None),
+        };
+
+        if offset.checked_add(length).map(|end| end > data.len()).unwrap_or(true) {
+            return Err(AppError::InvalidHeicFile("Exif item location out of bounds".to_string()));
+        }
+        let item = &data[offset..offset + length];
+
+        // The item payload begins with a 4-byte big-endian offset to the TIFF header.
+        if item.len() < 4 {
+            return Ok(None);
+        }
+        let tiff_offset = 4 + u32::from_be_bytes([item[0], item[1], item[2], item[3]]) as usize;
+        if tiff_offset > item.len() {
+            return Ok(None);
+        }
+
+        Ok(Some(item[tiff_offset..].to_vec()))
+    }
+
+    /// Parse the Orientation tag (0x0112) out of a raw Exif TIFF block.
+    pub fn parse_orientation(tiff: &[u8]) -> Option<u16> {
+        if tiff.len() < 8 {
+            return None;
+        }
+        let little_endian = match &tiff[0..2] {
+            b"II" => true,
+            b"MM" => false,
+            _ => return None,
+        };
+        let read_u16 = |b: &[u8]| if little_endian {
+            u16::from_le_bytes([b[0], b[1]])
+        } else {
+            u16::from_be_bytes([b[0], b[1]])
+        };
+        let read_u32 = |b: &[u8]| if little_endian {
+            u32::from_le_bytes([b[0], b[1], b[2], b[3]])
+        } else {
+            u32::from_be_bytes([b[0], b[1], b[2], b[3]])
+        };
+
+        let ifd0_offset = read_u32(&tiff[4..8]) as usize;
+        if ifd0_offset + 2 > tiff.len() {
+            return None;
+        }
+        let entry_count = read_u16(&tiff[ifd0_offset..ifd0_offset + 2]) as usize;
+
+        for i in 0..entry_count {
+            let entry_start = ifd0_offset + 2 + i * 12;
+            if entry_start + 12 > tiff.len() {
+                break;
+            }
+            let tag = read_u16(&tiff[entry_start..entry_start + 2]);
+            if tag == 0x0112 {
+                return Some(read_u16(&tiff[entry_start + 8..entry_start + 10]));
+            }
+        }
+        None
+    }
+
+    /// Re-emit a raw Exif TIFF block as a JPEG APP1 segment, inserted right
+    /// after the output file's SOI marker.
+    pub fn write_exif_to_jpeg(jpeg_path: &Path, tiff: &[u8]) -> AppResult<()> {
+        let mut data = fs::read(jpeg_path)?;
+        if data.len() < 2 || data[0] != 0xFF || data[1] != 0xD8 {
+            return Err(AppError::ConversionFailed("Output is not a valid JPEG file".to_string()));
+        }
+
+        let mut payload = b"Exif\0\0".to_vec();
+        payload.extend_from_slice(tiff);
+        let segment_len = payload.len() + 2;
+        if segment_len > u16::MAX as usize {
+            return Err(AppError::ConversionFailed("Exif payload too large for a JPEG APP1 segment".to_string()));
+        }
+
+        let mut app1 = vec![0xFF, 0xE1];
+        app1.extend_from_slice(&(segment_len as u16).to_be_bytes());
+        app1.extend_from_slice(&payload);
+
+        data.splice(2..2, app1);
+        fs::write(jpeg_path, data)?;
+        Ok(())
+    }
+
+    /// Find the first child box of type `box_type` within `data[start..end]`.
+    fn find_box(data: &[u8], start: usize, end: usize, box_type: &[u8; 4]) -> AppResult<Option<IsoBox>> {
+        let mut offset = start;
+        while offset + 8 <= end {
+            let size32 = u32::from_be_bytes([data[offset], data[offset + 1], data[offset + 2], data[offset + 3]]) as u64;
+            let this_type = &data[offset + 4..offset + 8];
+
+            let (header_len, box_size) = if size32 == 1 {
+                if offset + 16 > end {
+                    return Err(AppError::InvalidHeicFile("Truncated extended box size".to_string()));
+                }
+                let size64 = u64::from_be_bytes(data[offset + 8..offset + 16].try_into().unwrap());
+                (16usize, size64)
+            } else if size32 == 0 {
+                (8usize, (end - offset) as u64)
+            } else {
+                (8usize, size32)
+            };
+
+            let body_start = offset + header_len;
+            let box_end = offset.checked_add(box_size as usize)
+                .filter(|&e| e <= end && e >= body_start)
+                .ok_or_else(|| AppError::InvalidHeicFile("Box size exceeds container bounds".to_string()))?;
+
+            if this_type == box_type {
+                return Ok(Some(IsoBox { box_type: [this_type[0], this_type[1], this_type[2], this_type[3]], body_start, body_end: box_end }));
+            }
+
+            offset = box_end;
+        }
+        Ok(None)
+    }
+
+    /// Walk `iinf`'s `infe` children to find the item ID whose type is `Exif`.
+    fn find_exif_item_id(data: &[u8], iinf: &IsoBox) -> AppResult<Option<u32>> {
+        let base = iinf.body_start;
+        if base + 6 > iinf.body_end {
+            return Ok(None);
+        }
+        // iinf is a FullBox (4-byte version/flags) followed by a 2-byte entry count.
+        let version = data[base];
+        let entry_count = u16::from_be_bytes([data[base + 4], data[base + 5]]) as usize;
+        let mut offset = base + 6;
+        let _ = version;
+
+        for _ in 0..entry_count {
+            let infe = match Self::find_box(data, offset, iinf.body_end, b"infe")? {
+                Some(b) => b,
+                None => break,
+            };
+            offset = infe.body_end;
+
+            // infe (FullBox): version/flags(4), then item_ID (2 or 4 bytes
+            // depending on version), protection_index(2), item_type(4).
+            if infe.body_start >= infe.body_end {
+                continue;
+            }
+            let infe_version = data[infe.body_start];
+            let id_start = infe.body_start + 4;
+            let (item_id, type_start) = if infe_version >= 3 {
+                if id_start + 4 > infe.body_end { continue; }
+                (u32::from_be_bytes(data[id_start..id_start + 4].try_into().unwrap()), id_start + 4 + 2)
+            } else {
+                if id_start + 2 > infe.body_end { continue; }
+                (u16::from_be_bytes(data[id_start..id_start + 2].try_into().unwrap()) as u32, id_start + 2 + 2)
+            };
+
+            if type_start + 4 > infe.body_end {
+                continue;
+            }
+            if &data[type_start..type_start + 4] == b"Exif" {
+                let _ = iinf.box_type;
+                return Ok(Some(item_id));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Walk `iloc`'s item entries to find the (offset, length) of `item_id`.
+    /// Only the common single-extent case is handled.
+    fn find_item_location(data: &[u8], iloc: &IsoBox, item_id: u32) -> AppResult<Option<(usize, usize)>> {
+        let base = iloc.body_start;
+        if base + 6 > iloc.body_end {
+            return Ok(None);
+        }
+        let version = data[base];
+        let sizes_byte1 = data[base + 4];
+        let sizes_byte2 = data[base + 5];
+        let offset_size = (sizes_byte1 >> 4) as usize;
+        let length_size = (sizes_byte1 & 0x0F) as usize;
+        let base_offset_size = (sizes_byte2 >> 4) as usize;
+        let index_size = if version == 1 || version == 2 { (sizes_byte2 & 0x0F) as usize } else { 0 };
+
+        let mut offset = base + 6;
+        let item_count = if version < 2 {
+            if offset + 2 > iloc.body_end { return Ok(None); }
+            let count = u16::from_be_bytes([data[offset], data[offset + 1]]) as usize;
+            offset += 2;
+            count
+        } else {
+            if offset + 4 > iloc.body_end { return Ok(None); }
+            let count = u32::from_be_bytes(data[offset..offset + 4].try_into().unwrap()) as usize;
+            offset += 4;
+            count
+        };
+
+        for _ in 0..item_count {
+            let id_size = if version < 2 { 2 } else { 4 };
+            let this_id = Self::read_uint_checked(data, offset, id_size, iloc.body_end)? as u32;
+            offset += id_size;
+
+            if version == 1 || version == 2 {
+                Self::ensure_room(offset, 2, iloc.body_end)?;
+                offset += 2; // construction_method
+            }
+            Self::ensure_room(offset, 2, iloc.body_end)?;
+            offset += 2; // data_reference_index
+
+            let base_offset = Self::read_uint_checked(data, offset, base_offset_size, iloc.body_end)? as usize;
+            offset += base_offset_size;
+
+            Self::ensure_room(offset, 2, iloc.body_end)?;
+            let extent_count = u16::from_be_bytes([data[offset], data[offset + 1]]) as usize;
+            offset += 2;
+
+            let mut first_extent = None;
+            for _ in 0..extent_count {
+                Self::ensure_room(offset, index_size, iloc.body_end)?;
+                offset += index_size;
+
+                let extent_offset = Self::read_uint_checked(data, offset, offset_size, iloc.body_end)? as usize;
+                offset += offset_size;
+
+                let extent_length = Self::read_uint_checked(data, offset, length_size, iloc.body_end)? as usize;
+                offset += length_size;
+
+                if first_extent.is_none() {
+                    first_extent = Some((base_offset + extent_offset, extent_length));
+                }
+            }
+
+            if this_id == item_id {
+                return Ok(first_extent);
+            }
+        }
+        Ok(None)
+    }
+
+    /// Check that `size` bytes are available at `offset` before `end`,
+    /// without reading them.
+    fn ensure_room(offset: usize, size: usize, end: usize) -> AppResult<()> {
+        let safe = offset.checked_add(size).map(|stop| stop <= end).unwrap_or(false);
+        if safe {
+            Ok(())
+        } else {
+            Err(AppError::InvalidHeicFile("Truncated iloc box".to_string()))
+        }
+    }
+
+    /// Read a big-endian unsigned integer of `size` bytes (0-8) at `offset`,
+    /// bounds-checked against `end`.
+    fn read_uint_checked(data: &[u8], offset: usize, size: usize, end: usize) -> AppResult<u64> {
+        Self::ensure_room(offset, size, end)?;
+        let mut value: u64 = 0;
+        for i in 0..size {
+            value = (value << 8) | data[offset + i] as u64;
+        }
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn push_box(out: &mut Vec<u8>, box_type: &[u8; 4], body: &[u8]) {
+        let total_size = (8 + body.len()) as u32;
+        out.extend_from_slice(&total_size.to_be_bytes());
+        out.extend_from_slice(box_type);
+        out.extend_from_slice(body);
+    }
+
+    /// Builds a `meta/iinf/iloc` box tree whose single item is `Exif`-typed
+    /// and whose `iloc` entry claims one extent, but whose declared `iloc`
+    /// size ends exactly where the extent's offset/length bytes would start
+    /// (as if the source file were truncated mid-sync).
+    fn truncated_iloc_heic_bytes() -> Vec<u8> {
+        let mut infe_body = vec![2, 0, 0, 0]; // version/flags (infe version 2)
+        infe_body.extend_from_slice(&[0, 5]); // item_ID = 5
+        infe_body.extend_from_slice(&[0, 0]); // item_protection_index
+        infe_body.extend_from_slice(b"Exif"); // item_type
+        let mut infe = Vec::new();
+        push_box(&mut infe, b"infe", &infe_body);
+
+        let mut iinf_body = vec![0, 0, 0, 0]; // version/flags
+        iinf_body.extend_from_slice(&[0, 1]); // entry_count = 1
+        iinf_body.extend_from_slice(&infe);
+        let mut iinf = Vec::new();
+        push_box(&mut iinf, b"iinf", &iinf_body);
+
+        let mut iloc_body = vec![0, 0, 0, 0]; // version/flags (iloc version 0)
+        iloc_body.push(0x44); // offset_size=4, length_size=4
+        iloc_body.push(0x00); // base_offset_size=0, index_size=0
+        iloc_body.extend_from_slice(&[0, 1]); // item_count = 1
+        iloc_body.extend_from_slice(&[0, 5]); // item_ID = 5
+        iloc_body.extend_from_slice(&[0, 0]); // data_reference_index
+        iloc_body.extend_from_slice(&[0, 1]); // extent_count = 1
+        // Deliberately omit the extent's offset/length bytes: the box ends here.
+        let mut iloc = Vec::new();
+        push_box(&mut iloc, b"iloc", &iloc_body);
+
+        let mut meta_body = vec![0, 0, 0, 0]; // version/flags
+        meta_body.extend_from_slice(&iinf);
+        meta_body.extend_from_slice(&iloc);
+        let mut meta = Vec::new();
+        push_box(&mut meta, b"meta", &meta_body);
+
+        meta
+    }
+
+    #[test]
+    fn extract_exif_reports_error_instead_of_panicking_on_truncated_iloc() {
+        let data = truncated_iloc_heic_bytes();
+        let path = std::env::temp_dir().join(format!("exif_truncated_test_{}.heic", std::process::id()));
+        std::fs::write(&path, &data).unwrap();
+
+        let result = ExifHelper::extract_exif(&path);
+
+        std::fs::remove_file(&path).ok();
+        assert!(result.is_err(), "expected a graceful error, got {:?}", result);
+    }
+}
@@ -5,16 +5,29 @@ use std::io::Read;
 use uuid::Uuid;
 use log::{info, warn, error, debug};
 
+mod blurhash;
 mod config;
 mod constants;
 mod errors;
+mod exif;
 mod validation;
 
-use config::AppConfig;
+use blurhash::BlurhashHelper;
+use config::{AppConfig, OutputFormat};
 use constants::*;
 use errors::{AppError, AppResult};
+use exif::ExifHelper;
 use validation::ValidationHelper;
 
+/// Result of a single HEIC conversion: the converted file's path, plus an
+/// optional blurhash placeholder when `compute_blurhash` is enabled.
+#[derive(Debug, Clone, serde::Serialize)]
+struct ConversionOutput {
+    output_path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    blurhash: Option<String>,
+}
+
 // Validate and sanitize file paths using validation helper
 fn validate_file_path(file_path: &str) -> AppResult<PathBuf> {
     ValidationHelper::validate_path_safety(file_path)
@@ -22,24 +35,32 @@ fn validate_file_path(file_path: &str) -> AppResult<PathBuf> {
 
 // Validate HEIC/HEIF file by checking magic bytes
 fn validate_heic_file(file_path: &Path) -> AppResult<()> {
+    match detect_heic_brand(file_path)? {
+        Some(_) => Ok(()),
+        None => Err(AppError::InvalidHeicFile("Invalid HEIC/HEIF magic bytes".to_string())),
+    }
+}
+
+// Identify the ftyp brand (e.g. "heic", "heix", "mif1") of a HEIC/HEIF file
+// by checking its magic bytes, without enforcing that it's one we support.
+fn detect_heic_brand(file_path: &Path) -> AppResult<Option<String>> {
     let mut file = fs::File::open(file_path)?;
-    
+
     let mut buffer = [0u8; 12];
     if file.read_exact(&mut buffer).is_err() {
         return Err(AppError::InvalidHeicFile("File too small or unreadable".to_string()));
     }
-    
-    // Check for HEIC/HEIF magic bytes
+
     if buffer[HEIC_MAGIC_OFFSET..HEIC_MAGIC_OFFSET + HEIC_MAGIC_SIZE] == *HEIC_MAGIC_BYTES {
         let brand = &buffer[8..12];
         for supported_brand in HEIC_BRANDS {
             if brand.starts_with(supported_brand) {
-                return Ok(());
+                return Ok(Some(String::from_utf8_lossy(supported_brand).to_string()));
             }
         }
     }
-    
-    Err(AppError::InvalidHeicFile("Invalid HEIC/HEIF magic bytes".to_string()))
+
+    Ok(None)
 }
 
 #[tauri::command]
@@ -69,64 +90,196 @@ async fn get_file_size(file_path: String) -> Result<u64, String> {
 }
 
 #[tauri::command]
-async fn convert_heic_to_jpg(file_path: String) -> Result<String, String> {
-    match convert_heic_to_jpg_internal(file_path).await {
-        Ok(path) => Ok(path),
+async fn convert_heic_to_jpg(file_path: String) -> Result<ConversionOutput, String> {
+    let config = AppConfig::load();
+    match convert_heic_to_jpg_internal(file_path, &config).await {
+        Ok(result) => Ok(result),
         Err(e) => Err(e.into()),
     }
 }
 
-async fn convert_heic_to_jpg_internal(file_path: String) -> AppResult<String> {
+#[derive(Debug, Clone, serde::Serialize)]
+struct BatchResult {
+    file_path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    output_path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    blurhash: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<AppError>,
+}
+
+#[tauri::command]
+async fn convert_heic_batch(file_paths: Vec<String>) -> Result<Vec<BatchResult>, String> {
+    // Load the config once so every file in the batch converts under the
+    // same settings, even if `config.json` changes mid-batch.
+    let config = std::sync::Arc::new(AppConfig::load());
+    let max_concurrent = config.ui.max_concurrent_conversions.max(1) as usize;
+    info!("Starting batch conversion of {} file(s) with concurrency {}", file_paths.len(), max_concurrent);
+
+    let semaphore = std::sync::Arc::new(tokio::sync::Semaphore::new(max_concurrent));
+    let mut tasks = Vec::with_capacity(file_paths.len());
+
+    for file_path in file_paths {
+        let semaphore = semaphore.clone();
+        let config = config.clone();
+        let task_file_path = file_path.clone();
+        let handle = tokio::spawn(async move {
+            // Hold the permit for the duration of the conversion so at most
+            // `max_concurrent_conversions` run at once.
+            let _permit = semaphore.acquire_owned().await.expect("semaphore closed unexpectedly");
+            let result = convert_heic_to_jpg_internal(file_path.clone(), &config).await;
+            match result {
+                Ok(output) => BatchResult {
+                    file_path,
+                    output_path: Some(output.output_path),
+                    blurhash: output.blurhash,
+                    error: None,
+                },
+                Err(e) => {
+                    warn!("Batch conversion failed for {}: {}", file_path, e);
+                    BatchResult { file_path, output_path: None, blurhash: None, error: Some(e) }
+                }
+            }
+        });
+        tasks.push((task_file_path, handle));
+    }
+
+    let mut results = Vec::with_capacity(tasks.len());
+    for (file_path, task) in tasks {
+        match task.await {
+            Ok(batch_result) => results.push(batch_result),
+            Err(e) => {
+                error!("Batch conversion task panicked for {}: {}", file_path, e);
+                results.push(BatchResult {
+                    file_path,
+                    output_path: None,
+                    blurhash: None,
+                    error: Some(AppError::ConversionFailed(format!("Conversion task panicked: {}", e))),
+                });
+            }
+        }
+    }
+
+    info!("Batch conversion completed: {}/{} succeeded",
+          results.iter().filter(|r| r.error.is_none()).count(), results.len());
+    Ok(results)
+}
+
+async fn convert_heic_to_jpg_internal(file_path: String, config: &AppConfig) -> AppResult<ConversionOutput> {
     info!("Starting HEIC to JPEG conversion for: {}", file_path);
-    let config = AppConfig::load();
-    debug!("Using config: max_file_size={}MB, jpeg_quality={}", 
+    debug!("Using config: max_file_size={}MB, jpeg_quality={}",
            config.conversion.max_file_size_mb, config.conversion.jpeg_quality);
-    
+
     // Validate and sanitize the input path
     let input_path = validate_file_path(&file_path)?;
-    
+
     // Check file size limit (configurable)
     let max_file_size = config.max_file_size_bytes();
     let metadata = fs::metadata(&input_path)?;
     let file_size_mb = metadata.len() / (1024 * 1024);
     debug!("File size: {}MB", file_size_mb);
-    
+
     ValidationHelper::validate_file_size(metadata.len(), max_file_size)
         .map_err(|e| {
             warn!("File size validation failed: {}", e);
             e
         })?;
-    
+
     // Validate file extension using validation helper
     ValidationHelper::validate_extension(&input_path)?;
-    
+
     // Validate file content by checking magic bytes
     validate_heic_file(&input_path)?;
-    
+
     // Create a unique temporary filename for the converted file
-    let temp_filename = format!("{}_converted.jpg", Uuid::new_v4());
-    
+    let temp_filename = format!("{}_converted.{}", Uuid::new_v4(), config.conversion.output_format.extension());
+
     // Always save to temp directory for temporary storage
     let temp_dir = std::env::temp_dir();
     let output_path = temp_dir.join(&temp_filename);
-    
-    convert_heic_file(&input_path, &output_path, &config)?;
+
+    convert_heic_file(&input_path, &output_path, config)?;
+
+    if config.conversion.preserve_metadata {
+        preserve_exif_metadata(&input_path, &output_path, config);
+    }
+
+    let blurhash = if config.conversion.compute_blurhash {
+        compute_blurhash(&output_path)
+    } else {
+        None
+    };
+
     let output_path_str = output_path.to_string_lossy().to_string();
     info!("Conversion completed successfully: {}", output_path_str);
-    Ok(output_path_str)
+    Ok(ConversionOutput { output_path: output_path_str, blurhash })
+}
+
+/// Best-effort: compute a blurhash placeholder from the converted output.
+/// Failures are logged but never abort the conversion.
+fn compute_blurhash(output_path: &Path) -> Option<String> {
+    let img = match image::open(output_path) {
+        Ok(img) => img,
+        Err(e) => {
+            warn!("Failed to decode output for blurhash: {}", e);
+            return None;
+        }
+    };
+
+    match BlurhashHelper::encode(&img, BLURHASH_X_COMPONENTS, BLURHASH_Y_COMPONENTS) {
+        Ok(hash) => Some(hash),
+        Err(e) => {
+            warn!("Failed to compute blurhash: {}", e);
+            None
+        }
+    }
+}
+
+/// Best-effort: copy the source HEIC's Exif metadata into the converted
+/// output. Failures are logged but never abort the conversion, since the
+/// primary deliverable (the converted image) already succeeded.
+fn preserve_exif_metadata(input_path: &Path, output_path: &Path, config: &AppConfig) {
+    if config.conversion.output_format != OutputFormat::Jpeg {
+        debug!("Skipping Exif preservation: only JPEG output supports APP1 segments");
+        return;
+    }
+
+    match ExifHelper::extract_exif(input_path) {
+        Ok(Some(exif_data)) => {
+            if let Some(orientation) = ExifHelper::parse_orientation(&exif_data) {
+                debug!("Preserving Exif orientation {} from source", orientation);
+            }
+            if let Err(e) = ExifHelper::write_exif_to_jpeg(output_path, &exif_data) {
+                warn!("Failed to write Exif metadata to output: {}", e);
+            }
+        }
+        Ok(None) => debug!("No Exif metadata found in source HEIC"),
+        Err(e) => warn!("Failed to extract Exif metadata: {}", e),
+    }
 }
 
 fn convert_heic_file(input_path: &Path, output_path: &Path, config: &AppConfig) -> AppResult<()> {
     #[cfg(target_os = "macos")]
     {
-        debug!("Executing sips command with quality {}", config.conversion.jpeg_quality);
-        let output = Command::new("sips")
-            .arg("-s")
-            .arg("format")
-            .arg("jpeg")
-            .arg("-s")
-            .arg("formatOptions")
-            .arg(config.jpeg_quality_string())
+        let sips_format = config.conversion.output_format.sips_format().ok_or_else(|| {
+            AppError::ConversionFailed(format!(
+                "sips cannot encode {:?} output", config.conversion.output_format
+            ))
+        })?;
+        debug!("Executing sips command with format {} quality {}", sips_format, config.conversion.jpeg_quality);
+        let mut command = Command::new("sips");
+        command.arg("-s").arg("format").arg(sips_format);
+
+        if config.conversion.output_format.supports_quality() {
+            command.arg("-s").arg("formatOptions").arg(config.jpeg_quality_string());
+        }
+
+        if let Some(resize) = &config.conversion.resize {
+            command.arg("-Z").arg(resize.max_edge().to_string());
+        }
+
+        let output = command
             .arg(input_path)
             .arg("--out")
             .arg(output_path)
@@ -146,15 +299,70 @@ fn convert_heic_file(input_path: &Path, output_path: &Path, config: &AppConfig)
     
     #[cfg(not(target_os = "macos"))]
     {
-        if let Ok(img) = image::open(input_path) {
-            img.save(output_path)?;
-            Ok(())
-        } else {
-            Err(AppError::ConversionFailed(
-                "HEIC format not supported on this platform. Please use macOS with sips.".to_string()
-            ))
-        }
+        let img = decode_heic_with_libheif(input_path)?;
+        encode_image(&img, output_path, config)
+    }
+}
+
+/// Decode a HEIC file into an in-memory RGB image using libheif, for
+/// platforms where `sips` is unavailable.
+#[cfg(not(target_os = "macos"))]
+fn decode_heic_with_libheif(input_path: &Path) -> AppResult<image::DynamicImage> {
+    use libheif_rs::{ColorSpace, HeifContext, LibHeif, RgbChroma};
+
+    let lib_heif = LibHeif::new();
+    let ctx = HeifContext::read_from_file(&input_path.to_string_lossy())
+        .map_err(|e| AppError::ConversionFailed(format!("Failed to read HEIC file: {}", e)))?;
+    let handle = ctx
+        .primary_image_handle()
+        .map_err(|e| AppError::ConversionFailed(format!("Failed to get primary image handle: {}", e)))?;
+    let decoded = lib_heif
+        .decode(&handle, ColorSpace::Rgb(RgbChroma::Rgb), None)
+        .map_err(|e| AppError::ConversionFailed(format!("Failed to decode HEIC image: {}", e)))?;
+
+    let plane = decoded
+        .planes()
+        .interleaved
+        .ok_or_else(|| AppError::ConversionFailed("Decoded image has no interleaved RGB plane".to_string()))?;
+
+    let width = plane.width;
+    let height = plane.height;
+    let stride = plane.stride;
+    let row_bytes = width as usize * 3;
+
+    // The plane may be padded per row to `stride`, so copy row-by-row.
+    let mut rgb = Vec::with_capacity(row_bytes * height as usize);
+    for row in 0..height as usize {
+        let start = row * stride;
+        rgb.extend_from_slice(&plane.data[start..start + row_bytes]);
     }
+
+    let buffer = image::RgbImage::from_raw(width, height, rgb)
+        .ok_or_else(|| AppError::ConversionFailed("Failed to build image buffer from decoded HEIC data".to_string()))?;
+    Ok(image::DynamicImage::ImageRgb8(buffer))
+}
+
+/// Encode a decoded image to `output_path` in the configured output format,
+/// applying the configured resize preset first.
+#[cfg(not(target_os = "macos"))]
+fn encode_image(img: &image::DynamicImage, output_path: &Path, config: &AppConfig) -> AppResult<()> {
+    let resized;
+    let img = if let Some(preset) = &config.conversion.resize {
+        let max_edge = preset.max_edge();
+        resized = img.resize(max_edge, max_edge, image::imageops::FilterType::Lanczos3);
+        &resized
+    } else {
+        img
+    };
+
+    if config.conversion.output_format == OutputFormat::Jpeg {
+        let file = fs::File::create(output_path)?;
+        let mut encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(file, config.conversion.jpeg_quality);
+        encoder.encode_image(img)?;
+    } else {
+        img.save(output_path)?;
+    }
+    Ok(())
 }
 
 #[tauri::command]
@@ -207,6 +415,113 @@ async fn get_app_config() -> Result<AppConfig, String> {
     Ok(AppConfig::load())
 }
 
+#[tauri::command]
+async fn get_supported_output_formats() -> Result<Vec<String>, String> {
+    // On macOS, conversion shells out to `sips`, which has no AVIF encoder;
+    // elsewhere the `image` crate backs every `OutputFormat` variant.
+    Ok(OutputFormat::ALL
+        .iter()
+        .filter(|format| !cfg!(target_os = "macos") || format.sips_format().is_some())
+        .map(|format| format.extension().to_string())
+        .collect())
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+struct ConversionRecord {
+    #[serde(rename = "inputPath")]
+    input_path: String,
+    #[serde(rename = "outputPath")]
+    output_path: String,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+struct FileStats {
+    #[serde(rename = "inputPath")]
+    input_path: String,
+    #[serde(rename = "inputBytes")]
+    input_bytes: u64,
+    #[serde(rename = "outputBytes")]
+    output_bytes: u64,
+    #[serde(rename = "reductionPercent")]
+    reduction_percent: f64,
+}
+
+#[derive(Debug, Clone, Default, serde::Serialize)]
+struct ConversionStats {
+    #[serde(rename = "totalInputBytes")]
+    total_input_bytes: u64,
+    #[serde(rename = "totalOutputBytes")]
+    total_output_bytes: u64,
+    #[serde(rename = "aggregateReductionPercent")]
+    aggregate_reduction_percent: f64,
+    #[serde(rename = "brandCounts")]
+    brand_counts: std::collections::HashMap<String, u32>,
+    files: Vec<FileStats>,
+}
+
+#[tauri::command]
+async fn get_conversion_stats(entries: Vec<ConversionRecord>) -> Result<ConversionStats, String> {
+    let mut stats = ConversionStats::default();
+
+    for entry in entries {
+        let input_path = match validate_file_path(&entry.input_path) {
+            Ok(path) => path,
+            Err(e) => {
+                warn!("Skipping stats for invalid input path {}: {}", entry.input_path, e);
+                continue;
+            }
+        };
+        let output_path = match validate_file_path(&entry.output_path) {
+            Ok(path) => path,
+            Err(e) => {
+                warn!("Skipping stats for invalid output path {}: {}", entry.output_path, e);
+                continue;
+            }
+        };
+
+        let input_bytes = match fs::metadata(&input_path) {
+            Ok(metadata) => metadata.len(),
+            Err(_) => {
+                warn!("Skipping stats for missing input file: {}", entry.input_path);
+                continue;
+            }
+        };
+        let output_bytes = match fs::metadata(&output_path) {
+            Ok(metadata) => metadata.len(),
+            Err(_) => {
+                warn!("Skipping stats for missing output file: {}", entry.output_path);
+                continue;
+            }
+        };
+
+        let brand = detect_heic_brand(&input_path)
+            .ok()
+            .flatten()
+            .unwrap_or_else(|| "unknown".to_string());
+        *stats.brand_counts.entry(brand).or_insert(0) += 1;
+
+        stats.total_input_bytes += input_bytes;
+        stats.total_output_bytes += output_bytes;
+        stats.files.push(FileStats {
+            input_path: entry.input_path,
+            input_bytes,
+            output_bytes,
+            reduction_percent: reduction_percent(input_bytes, output_bytes),
+        });
+    }
+
+    stats.aggregate_reduction_percent = reduction_percent(stats.total_input_bytes, stats.total_output_bytes);
+    Ok(stats)
+}
+
+fn reduction_percent(input_bytes: u64, output_bytes: u64) -> f64 {
+    if input_bytes == 0 {
+        0.0
+    } else {
+        (1.0 - output_bytes as f64 / input_bytes as f64) * 100.0
+    }
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     // Initialize logger
@@ -219,7 +534,7 @@ pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_dialog::init())
-        .invoke_handler(tauri::generate_handler![save_temp_file, convert_heic_to_jpg, download_file, cleanup_temp_file, get_file_size, get_app_config])
+        .invoke_handler(tauri::generate_handler![save_temp_file, convert_heic_to_jpg, convert_heic_batch, download_file, cleanup_temp_file, get_file_size, get_app_config, get_supported_output_formats, get_conversion_stats])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }